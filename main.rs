@@ -1,5 +1,4 @@
 // Entry point for non-wasm
-//NEXT STEP - FIGURE OUT HOW TO ROTATE MESHES
 #[cfg(not(target_arch = "wasm32"))]
 #[tokio::main]
 async fn main() {
@@ -7,8 +6,16 @@ async fn main() {
 }
 
 use three_d::*;
-use rand::Rng;
-use web_sys::console;
+use rand::{Rng, SeedableRng};
+
+// Grid spacing for laying the network out: distance between layers, then
+// between neurons within a layer.
+const LAYER_SPACING: f32 = 4.5;
+const NEURON_SPACING: f32 = 2.0;
+
+// How long "Run" trains before giving up on a dataset that never converges.
+const MAX_TRAINING_EPOCHS: usize = 2000;
+const STALL_WINDOW: usize = 50;
 
 struct Perceptron {
     weights: Vec<f64>,
@@ -25,38 +32,764 @@ impl Perceptron {
         }
     }
 
-    fn predict(&self, inputs: &[f64]) -> i32 {
-        let sum = self
-            .weights
+    fn weighted_sum(&self, inputs: &[f64]) -> f64 {
+        self.weights
             .iter()
             .zip(inputs.iter())
             .map(|(&w, &x)| w * x)
-            .sum::<f64>();
-        if sum >= 0.0 {
-            1
-        } else {
-            -1
+            .sum()
+    }
+
+    fn train(&mut self, inputs: &[f64], error: f64) {
+        for (weight, &x) in self.weights.iter_mut().zip(inputs.iter()) {
+            *weight += self.learning_rate * error * x;
+        }
+    }
+}
+
+// A layer's non-linearity. Only `Step` is used today.
+enum Activation {
+    Step,
+}
+
+impl Activation {
+    fn apply(&self, sum: f64) -> f64 {
+        match self {
+            Activation::Step => {
+                if sum >= 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
         }
     }
+}
 
-    fn train(&mut self, inputs: &[&[f64]], targets: &[i32], max_epochs: usize) {
-        for _ in 0..max_epochs {
-            let mut error_count = 0;
-            for (&input, &target) in inputs.iter().zip(targets.iter()) {
-                let prediction = self.predict(input);
-                let error = target - prediction;
-                if error != 0 {
-                    error_count += 1;
-                    for (weight, &x) in self.weights.iter_mut().zip(input.iter()) {
-                        *weight += self.learning_rate * (error as f64) * x;
+struct Layer {
+    perceptrons: Vec<Perceptron>,
+    activation: Activation,
+}
+
+impl Layer {
+    fn new(num_neurons: usize, num_inputs_per_neuron: usize, learning_rate: f64, activation: Activation) -> Self {
+        let perceptrons = (0..num_neurons)
+            .map(|_| Perceptron::new(num_inputs_per_neuron, learning_rate))
+            .collect();
+        Layer {
+            perceptrons,
+            activation,
+        }
+    }
+
+    fn forward(&self, inputs: &[f64]) -> Vec<f64> {
+        self.perceptrons
+            .iter()
+            .map(|p| self.activation.apply(p.weighted_sum(inputs)))
+            .collect()
+    }
+}
+
+// A multi-layer network built from a layer-size spec, e.g. `[2, 3, 1]` is a
+// network with 2 inputs, one hidden layer of 3 neurons, and 1 output.
+// `layer_sizes[0]` is the input count and has no `Layer` of its own.
+struct Network {
+    layer_sizes: Vec<usize>,
+    layers: Vec<Layer>,
+}
+
+impl Network {
+    fn new(layer_sizes: &[usize], learning_rate: f64) -> Self {
+        assert!(
+            layer_sizes.len() >= 2,
+            "a network needs at least an input layer and an output layer"
+        );
+        let layers = layer_sizes
+            .windows(2)
+            .map(|pair| Layer::new(pair[1], pair[0], learning_rate, Activation::Step))
+            .collect();
+        Network {
+            layer_sizes: layer_sizes.to_vec(),
+            layers,
+        }
+    }
+
+    fn forward(&self, inputs: &[f64]) -> Vec<f64> {
+        let mut activations = inputs.to_vec();
+        for layer in &self.layers {
+            activations = layer.forward(&activations);
+        }
+        activations
+    }
+
+    // Collapses `forward`'s output vector down to a single classification by
+    // reading output neuron 0 only; for multi-output predictions, call
+    // `forward` directly instead.
+    fn predict(&self, inputs: &[f64]) -> i32 {
+        self.forward(inputs)[0] as i32
+    }
+
+    // Activation of every node in the rendered graph, in the same order
+    // `build_network_scene` assigns `NodeRef`s: raw inputs, then each
+    // layer's outputs in turn.
+    fn activations(&self, inputs: &[f64]) -> Vec<f64> {
+        let mut all = inputs.to_vec();
+        let mut activations = inputs.to_vec();
+        for layer in &self.layers {
+            activations = layer.forward(&activations);
+            all.extend(activations.iter().copied());
+        }
+        all
+    }
+
+    // Runs a single epoch of the perceptron delta rule across every layer
+    // and returns how many training examples were misclassified, so callers
+    // can step through training one epoch at a time.
+    fn train_epoch(&mut self, inputs: &[&[f64]], targets: &[i32]) -> usize {
+        let mut error_count = 0;
+        for (&input, &target) in inputs.iter().zip(targets.iter()) {
+            if target - self.predict(input) != 0 {
+                error_count += 1;
+            }
+            let mut layer_inputs = input.to_vec();
+            for layer in &mut self.layers {
+                let outputs = layer.forward(&layer_inputs);
+                for (perceptron, &output) in layer.perceptrons.iter_mut().zip(outputs.iter()) {
+                    let error = target as f64 - output;
+                    if error != 0.0 {
+                        perceptron.train(&layer_inputs, error);
                     }
                 }
+                layer_inputs = outputs;
             }
-            if error_count == 0 {
-                break;
+        }
+        error_count
+    }
+}
+
+// Identifies what a node mesh represents, for the click handler.
+#[derive(Clone, Copy, Debug)]
+enum NodeRef {
+    Input(usize),
+    Neuron { layer: usize, neuron: usize },
+}
+
+// Identifies a weight edge: `layer` indexes into `Network::layers`, `from`
+// and `to` are the neuron indices on either side of it.
+#[derive(Clone, Copy, Debug)]
+struct EdgeRef {
+    layer: usize,
+    from: usize,
+    to: usize,
+}
+
+// The procedurally generated meshes for a `Network`, plus bookkeeping to map
+// a picked `Gm` back to the `(layer, neuron)` or edge it represents. Each
+// node/edge has a matching wireframe overlay mesh for hover/lock highlights.
+struct NetworkScene {
+    node_meshes: Vec<Gm<Mesh, GlowMaterial>>,
+    node_refs: Vec<NodeRef>,
+    node_highlights: Vec<Gm<Mesh, WireframeMaterial>>,
+    edge_meshes: Vec<Gm<Mesh, PhysicalMaterial>>,
+    edge_refs: Vec<EdgeRef>,
+    edge_highlights: Vec<Gm<Mesh, WireframeMaterial>>,
+}
+
+fn node_position(layer_index: usize, neuron_index: usize) -> Vec3 {
+    vec3(
+        -3.0 + layer_index as f32 * LAYER_SPACING,
+        neuron_index as f32 * NEURON_SPACING,
+        0.0,
+    )
+}
+
+fn default_material() -> CpuMaterial {
+    CpuMaterial {
+        albedo: Color::new(128, 128, 128, 128),
+        ..Default::default()
+    }
+}
+
+// Rotates `CpuMesh::cylinder`'s default +X orientation onto the direction
+// between `a` and `b`, then scales and translates it into place.
+fn edge_transform(a: Vec3, b: Vec3, radius: f32) -> Mat4 {
+    let d = b - a;
+    let len = d.magnitude();
+    let dir = d / len;
+    let x_axis = vec3(1.0, 0.0, 0.0);
+
+    let dot = x_axis.dot(dir).clamp(-1.0, 1.0);
+    let rotation = if dot > 1.0 - 1e-6 {
+        // dir is already +X - no rotation needed.
+        Mat4::identity()
+    } else if dot < -1.0 + 1e-6 {
+        // dir is -X - axis is undefined, so flip 180 degrees about any axis
+        // perpendicular to X, e.g. Y.
+        Mat4::from_axis_angle(vec3(0.0, 1.0, 0.0), degrees(180.0))
+    } else {
+        let axis = x_axis.cross(dir).normalize();
+        let angle = radians(dot.acos());
+        Mat4::from_axis_angle(axis, angle)
+    };
+
+    Mat4::from_translation(a) * rotation * Mat4::from_nonuniform_scale(len, radius, radius)
+}
+
+// De-indexes a mesh's triangles and packs each vertex's triangle-corner
+// (1,0,0)/(0,1,0)/(0,0,1) into its color channel, for `WireframeMaterial` to
+// read back as a barycentric coordinate.
+fn debarycentric_mesh(mesh: &CpuMesh) -> CpuMesh {
+    let positions = mesh.positions.to_f32();
+    let indices: Vec<u32> = match &mesh.indices {
+        Indices::U32(indices) => indices.clone(),
+        Indices::U16(indices) => indices.iter().map(|&i| i as u32).collect(),
+        Indices::U8(indices) => indices.iter().map(|&i| i as u32).collect(),
+        Indices::None => (0..positions.len() as u32).collect(),
+    };
+
+    let corners = [
+        Color::new(255, 0, 0, 255),
+        Color::new(0, 255, 0, 255),
+        Color::new(0, 0, 255, 255),
+    ];
+
+    let mut flat_positions = Vec::with_capacity(indices.len());
+    let mut barycentric_colors = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        for (corner, &index) in triangle.iter().enumerate() {
+            flat_positions.push(positions[index as usize]);
+            barycentric_colors.push(corners[corner]);
+        }
+    }
+
+    CpuMesh {
+        positions: Positions::F32(flat_positions),
+        colors: Some(barycentric_colors),
+        ..Default::default()
+    }
+}
+
+// Draws a crisp edge-highlight outline over a mesh via the barycentric
+// wireframe trick: `fwidth` on the packed barycentric coordinate gives the
+// fragment's distance to the nearest triangle edge.
+struct WireframeMaterial {
+    color: Color,
+}
+
+const WIREFRAME_FRAGMENT_SHADER: &str = r#"
+    in vec4 col;
+    uniform vec4 highlightColor;
+    layout (location = 0) out vec4 outColor;
+    void main() {
+        vec3 bary = col.rgb;
+        vec3 d = fwidth(bary);
+        vec3 a3 = smoothstep(vec3(0.0), 0.8 * d, bary);
+        float edge = 1.0 - min(min(a3.x, a3.y), a3.z);
+        if (edge < 0.05) {
+            discard;
+        }
+        outColor = vec4(highlightColor.rgb, highlightColor.a * edge);
+    }
+"#;
+
+impl Material for WireframeMaterial {
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        WIREFRAME_FRAGMENT_SHADER.to_string()
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            color: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_uniform("highlightColor", self.color);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            write_mask: WriteMask::COLOR,
+            blend: Blend::TRANSPARENCY,
+            cull: Cull::Back,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Transparent
+    }
+}
+
+// Unlit material that writes `color * intensity` straight into the HDR
+// target uncapped, so `intensity` above 1.0 feeds `render_with_bloom`'s
+// bright-pass threshold.
+struct GlowMaterial {
+    color: Color,
+    intensity: f32,
+}
+
+const GLOW_FRAGMENT_SHADER: &str = r#"
+    uniform vec4 baseColor;
+    uniform float intensity;
+    layout (location = 0) out vec4 outColor;
+    void main() {
+        outColor = vec4(baseColor.rgb * intensity, 1.0);
+    }
+"#;
+
+impl Material for GlowMaterial {
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        GLOW_FRAGMENT_SHADER.to_string()
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes::NONE
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        program.use_uniform("baseColor", self.color);
+        program.use_uniform("intensity", self.intensity);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            cull: Cull::Back,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+fn build_network_scene(context: &Context, network: &Network) -> NetworkScene {
+    let mut node_meshes = Vec::new();
+    let mut node_refs = Vec::new();
+    let mut node_highlights = Vec::new();
+
+    for (layer_index, &neuron_count) in network.layer_sizes.iter().enumerate() {
+        for neuron_index in 0..neuron_count {
+            let mut sphere = CpuMesh::sphere(16);
+            sphere
+                .transform(&Mat4::from_translation(node_position(layer_index, neuron_index)))
+                .unwrap();
+            node_highlights.push(Gm::new(
+                Mesh::new(context, &debarycentric_mesh(&sphere)),
+                WireframeMaterial {
+                    color: Color::new(255, 215, 80, 160),
+                },
+            ));
+            node_meshes.push(Gm::new(
+                Mesh::new(context, &sphere),
+                GlowMaterial {
+                    color: Color::new(160, 110, 40, 255),
+                    intensity: 0.1,
+                },
+            ));
+            node_refs.push(if layer_index == 0 {
+                NodeRef::Input(neuron_index)
+            } else {
+                NodeRef::Neuron {
+                    layer: layer_index - 1,
+                    neuron: neuron_index,
+                }
+            });
+        }
+    }
+
+    let mut edge_meshes = Vec::new();
+    let mut edge_refs = Vec::new();
+    let mut edge_highlights = Vec::new();
+
+    for (layer_index, layer) in network.layers.iter().enumerate() {
+        let from_count = network.layer_sizes[layer_index];
+        for to in 0..layer.perceptrons.len() {
+            for from in 0..from_count {
+                let a = node_position(layer_index, from);
+                let b = node_position(layer_index + 1, to);
+                let mut edge = CpuMesh::cylinder(8);
+                edge.transform(&edge_transform(a, b, 0.1)).unwrap();
+                edge_highlights.push(Gm::new(
+                    Mesh::new(context, &debarycentric_mesh(&edge)),
+                    WireframeMaterial {
+                        color: Color::new(255, 215, 80, 160),
+                    },
+                ));
+                edge_meshes.push(Gm::new(
+                    Mesh::new(context, &edge),
+                    PhysicalMaterial::new_opaque(context, &default_material()),
+                ));
+                edge_refs.push(EdgeRef {
+                    layer: layer_index,
+                    from,
+                    to,
+                });
+            }
+        }
+    }
+
+    NetworkScene {
+        node_meshes,
+        node_refs,
+        node_highlights,
+        edge_meshes,
+        edge_refs,
+        edge_highlights,
+    }
+}
+
+// Runs one epoch, updating the stall-detection counters shared by the
+// "Step epoch" button and the "Run" auto-stepper.
+fn step_training(
+    network: &mut Network,
+    inputs: &[&[f64]],
+    targets: &[i32],
+    history: &mut Vec<f64>,
+    best_errors: &mut usize,
+    epochs_since_improvement: &mut usize,
+) -> usize {
+    let errors = network.train_epoch(inputs, targets);
+    history.push(errors as f64);
+    if errors < *best_errors {
+        *best_errors = errors;
+        *epochs_since_improvement = 0;
+    } else {
+        *epochs_since_improvement += 1;
+    }
+    errors
+}
+
+// Red for a positive weight, blue for a negative one, scaled by magnitude.
+fn weight_color(weight: f64) -> Color {
+    let intensity = (80.0 + (weight.abs() / 2.0).min(1.0) * 175.0) as u8;
+    if weight >= 0.0 {
+        Color::new(intensity, 60, 60, 255)
+    } else {
+        Color::new(60, 60, intensity, 255)
+    }
+}
+
+fn refresh_edge_colors(scene: &mut NetworkScene, network: &Network) {
+    for (mesh, edge_ref) in scene.edge_meshes.iter_mut().zip(scene.edge_refs.iter()) {
+        let weight = network.layers[edge_ref.layer].perceptrons[edge_ref.to].weights[edge_ref.from];
+        mesh.material.albedo = weight_color(weight);
+    }
+}
+
+// Sets each node's glow intensity from how strongly it's firing.
+fn refresh_node_glow(scene: &mut NetworkScene, network: &Network, inputs: &[f64]) {
+    for (mesh, activation) in scene.node_meshes.iter_mut().zip(network.activations(inputs)) {
+        mesh.material.intensity = if activation > 0.0 {
+            1.0 + activation.abs() as f32
+        } else {
+            0.1
+        };
+    }
+}
+
+// Colors the hovered/locked wireframe overlays and returns the deduplicated
+// set that should actually be drawn this frame.
+fn highlight_objects(
+    highlights: &mut [Gm<Mesh, WireframeMaterial>],
+    hovered: Option<usize>,
+    locked: Option<usize>,
+) -> Vec<&dyn Object> {
+    let hover_color = Color::new(255, 215, 80, 160);
+    let lock_color = Color::new(255, 90, 30, 220);
+
+    if let Some(i) = hovered {
+        if Some(i) != locked {
+            highlights[i].material.color = hover_color;
+        }
+    }
+    if let Some(i) = locked {
+        highlights[i].material.color = lock_color;
+    }
+
+    let mut indices: Vec<usize> = hovered.into_iter().chain(locked).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices.into_iter().map(|i| &highlights[i] as &dyn Object).collect()
+}
+
+// Bloom threshold/intensity exposed on the control panel.
+struct BloomSettings {
+    threshold: f32,
+    intensity: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            threshold: 1.0,
+            intensity: 0.6,
+        }
+    }
+}
+
+const BRIGHT_PASS_FRAGMENT_SHADER: &str = r#"
+    uniform sampler2D colorMap;
+    uniform float threshold;
+    in vec2 uvs;
+    layout (location = 0) out vec4 color;
+    void main() {
+        vec4 hdr = texture(colorMap, uvs);
+        float luminance = dot(hdr.rgb, vec3(0.2126, 0.7152, 0.0722));
+        color = luminance > threshold ? hdr : vec4(0.0, 0.0, 0.0, 1.0);
+    }
+"#;
+
+const BLUR_FRAGMENT_SHADER: &str = r#"
+    uniform sampler2D colorMap;
+    uniform vec2 direction;
+    in vec2 uvs;
+    layout (location = 0) out vec4 color;
+    void main() {
+        vec2 texel = direction / vec2(textureSize(colorMap, 0));
+        vec4 sum = texture(colorMap, uvs) * 0.227027;
+        sum += texture(colorMap, uvs + texel * 1.384615) * 0.316216;
+        sum += texture(colorMap, uvs - texel * 1.384615) * 0.316216;
+        sum += texture(colorMap, uvs + texel * 3.230769) * 0.070270;
+        sum += texture(colorMap, uvs - texel * 3.230769) * 0.070270;
+        color = sum;
+    }
+"#;
+
+const COMPOSITE_FRAGMENT_SHADER: &str = r#"
+    uniform sampler2D colorMap;
+    uniform sampler2D bloomMap;
+    uniform float intensity;
+    in vec2 uvs;
+    layout (location = 0) out vec4 color;
+    void main() {
+        vec3 hdr = texture(colorMap, uvs).rgb + intensity * texture(bloomMap, uvs).rgb;
+        vec3 mapped = hdr / (hdr + vec3(1.0));
+        color = vec4(mapped, 1.0);
+    }
+"#;
+
+// Off-screen HDR target plus the ping-pong blur textures, rebuilt on resize.
+struct BloomPipeline {
+    hdr_color: Texture2D,
+    hdr_depth: DepthTexture2D,
+    bright: Texture2D,
+    blur_a: Texture2D,
+    blur_b: Texture2D,
+    viewport: Viewport,
+}
+
+impl BloomPipeline {
+    fn new(context: &Context, viewport: Viewport) -> Self {
+        let hdr_texture = |context: &Context| {
+            Texture2D::new_empty::<[f32; 4]>(
+                context,
+                viewport.width,
+                viewport.height,
+                Interpolation::Linear,
+                Interpolation::Linear,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            )
+        };
+        BloomPipeline {
+            hdr_color: hdr_texture(context),
+            hdr_depth: DepthTexture2D::new::<f32>(
+                context,
+                viewport.width,
+                viewport.height,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+            bright: hdr_texture(context),
+            blur_a: hdr_texture(context),
+            blur_b: hdr_texture(context),
+            viewport,
+        }
+    }
+}
+
+// Renders `objects` into an HDR target, extracts and blurs pixels above
+// `settings.threshold`, and composites the glow back over `screen`.
+fn render_with_bloom(
+    pipeline: &mut BloomPipeline,
+    context: &Context,
+    screen: &mut RenderTarget,
+    camera: &Camera,
+    objects: &[&dyn Object],
+    lights: &[&dyn Light],
+    settings: &BloomSettings,
+) {
+    if pipeline.viewport != camera.viewport() {
+        *pipeline = BloomPipeline::new(context, camera.viewport());
+    }
+
+    RenderTarget::new(
+        pipeline.hdr_color.as_color_target(None),
+        pipeline.hdr_depth.as_depth_target(),
+    )
+    .clear(ClearState::color_and_depth(1.0, 1.0, 1.0, 1.0, 1.0))
+    .render(camera, objects, lights);
+
+    pipeline
+        .bright
+        .as_color_target(None)
+        .as_render_target()
+        .write(|| {
+            apply_effect(
+                context,
+                BRIGHT_PASS_FRAGMENT_SHADER,
+                RenderStates::default(),
+                pipeline.viewport,
+                |program| {
+                    program.use_texture("colorMap", &pipeline.hdr_color);
+                    program.use_uniform("threshold", settings.threshold);
+                },
+            );
+        });
+
+    pipeline
+        .blur_a
+        .as_color_target(None)
+        .as_render_target()
+        .write(|| {
+            apply_effect(
+                context,
+                BLUR_FRAGMENT_SHADER,
+                RenderStates::default(),
+                pipeline.viewport,
+                |program| {
+                    program.use_texture("colorMap", &pipeline.bright);
+                    program.use_uniform("direction", vec2(1.0, 0.0));
+                },
+            );
+        });
+    pipeline
+        .blur_b
+        .as_color_target(None)
+        .as_render_target()
+        .write(|| {
+            apply_effect(
+                context,
+                BLUR_FRAGMENT_SHADER,
+                RenderStates::default(),
+                pipeline.viewport,
+                |program| {
+                    program.use_texture("colorMap", &pipeline.blur_a);
+                    program.use_uniform("direction", vec2(0.0, 1.0));
+                },
+            );
+        });
+
+    screen.write(|| {
+        apply_effect(
+            context,
+            COMPOSITE_FRAGMENT_SHADER,
+            RenderStates::default(),
+            pipeline.viewport,
+            |program| {
+                program.use_texture("colorMap", &pipeline.hdr_color);
+                program.use_texture("bloomMap", &pipeline.blur_b);
+                program.use_uniform("intensity", settings.intensity);
+            },
+        );
+    });
+}
+
+// Whether the decision-boundary cloud fills the whole input domain or keeps
+// only the points that sit right on a class transition.
+#[derive(Clone, Copy, PartialEq)]
+enum SampleMode {
+    Interior,
+    Boundary,
+}
+
+// Scatters points across the input domain, classified by the current model
+// into a live picture of the learned decision boundary. The seed is stored
+// so the same cloud reappears across frames and mode toggles.
+struct BoundarySampler {
+    seed: u64,
+    mode: SampleMode,
+    sample_count: usize,
+    domain_min: f64,
+    domain_max: f64,
+    boundary_epsilon: f64,
+}
+
+impl BoundarySampler {
+    fn new(seed: u64) -> Self {
+        BoundarySampler {
+            seed,
+            mode: SampleMode::Interior,
+            sample_count: 20_000,
+            domain_min: -0.5,
+            domain_max: 1.5,
+            boundary_epsilon: 0.02,
+        }
+    }
+
+    fn sample(&self, network: &Network, num_inputs: usize) -> (Vec<Vec3>, Vec<Color>) {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(self.seed);
+        let mut positions = Vec::new();
+        let mut colors = Vec::new();
+
+        for _ in 0..self.sample_count {
+            let point: Vec<f64> = (0..num_inputs)
+                .map(|_| rng.gen_range(self.domain_min..self.domain_max))
+                .collect();
+            let prediction = network.predict(&point);
+
+            let keep = match self.mode {
+                SampleMode::Interior => true,
+                SampleMode::Boundary => (0..num_inputs).any(|axis| {
+                    let mut nudged = point.clone();
+                    nudged[axis] += self.boundary_epsilon;
+                    network.predict(&nudged) != prediction
+                }),
+            };
+
+            if !keep {
+                continue;
             }
+
+            let x = point.first().copied().unwrap_or(0.0) as f32;
+            let z = point.get(1).copied().unwrap_or(0.0) as f32;
+            positions.push(vec3(x - 3.0, -2.5, z));
+            colors.push(if prediction >= 0 {
+                Color::new(200, 40, 40, 255)
+            } else {
+                Color::new(40, 40, 200, 255)
+            });
         }
-    }    
+
+        (positions, colors)
+    }
+}
+
+fn build_sample_points(
+    context: &Context,
+    sampler: &BoundarySampler,
+    network: &Network,
+    num_inputs: usize,
+) -> Gm<InstancedMesh, ColorMaterial> {
+    let (positions, colors) = sampler.sample(network, num_inputs);
+
+    let mut point_mesh = CpuMesh::sphere(4);
+    point_mesh.transform(&Mat4::from_scale(0.04)).unwrap();
+
+    let instances = Instances {
+        transformations: positions.into_iter().map(Mat4::from_translation).collect(),
+        colors: Some(colors),
+        ..Default::default()
+    };
+
+    Gm::new(
+        InstancedMesh::new(context, &instances, &point_mesh),
+        ColorMaterial::default(),
+    )
 }
 
 pub async fn run() {
@@ -84,7 +817,7 @@ pub async fn run() {
     let ambient = AmbientLight::new(&context, 0.4, Color::WHITE);
     let directional = DirectionalLight::new(&context, 2.0, Color::WHITE, &vec3(-1.0, -1.0, -1.0));
 
-    //Instatiate Perceptron, Load training data into the heap, and train the perceptron
+    //Instantiate Network and load training data onto the heap
     let inputs: [&[f64]; 4] = [
     &[0.0, 0.0],
     &[0.0, 1.0],
@@ -93,152 +826,114 @@ pub async fn run() {
     ];
 
     let targets = [1, -1, -1, 1];
+    let num_inputs = inputs[0].len();
+    let layer_sizes = vec![num_inputs, 1];
+    let learning_rate = 0.1;
 
-    let mut perceptron = Perceptron::new(inputs[0].len(), 0.1);
-    perceptron.train(&inputs, &targets, 100);
-
-    for input in &inputs {
-        let prediction = perceptron.predict(input);
-        println!("Input: {:?} => Prediction: {}", input, prediction);
-    }
-
-    //Set up shape meshes
-    let mut sphere = CpuMesh::sphere(16);
-    sphere.transform(&Mat4::from_translation(Vec3::new(-3.0, 0.0, 0.0))).unwrap();
-    let mut sphere_mesh = Gm::new(
-        Mesh::new(&context, &sphere),
-        PhysicalMaterial::new_opaque(
-            &context,
-            &CpuMaterial {
-                albedo: Color::new(128,128,128,128),
-                ..Default::default()
-            },
-        ),
-    );
-
-    let mut input_node_1 = CpuMesh::sphere(16);
-    input_node_1.transform(&Mat4::from_translation(Vec3::new(-3.0, 2.0, 0.0))).unwrap();
-
-    let mut input_node_1_mesh = Gm::new(
-        Mesh::new(&context, &input_node_1),
-        PhysicalMaterial::new_opaque(
-            &context,
-            &CpuMaterial {
-                albedo: Color::new(128,128,128,128),
-                ..Default::default()
-            },
-        ),
-    );
+    let mut network = Network::new(&layer_sizes, learning_rate);
+    let mut training_history: Vec<f64> = Vec::new();
+    let mut training_running = false;
+    let mut best_errors = usize::MAX;
+    let mut epochs_since_improvement = 0;
+    let mut training_stalled = false;
 
-    let mut output_node = CpuMesh::sphere(16);
-    output_node.transform(&Mat4::from_translation(Vec3::new(1.5, 0.0, 0.0))).unwrap();
+    //Set up node/edge meshes for the network's layout, colored by its
+    //(initially random) weights
+    let mut scene = build_network_scene(&context, &network);
+    refresh_edge_colors(&mut scene, &network);
+    let mut current_input_index = 0;
+    refresh_node_glow(&mut scene, &network, inputs[current_input_index]);
 
-    let mut output_node_mesh = Gm::new(
-        Mesh::new(&context, &output_node),
-        PhysicalMaterial::new_opaque(
-            &context,
-            &CpuMaterial {
-                albedo: Color::new(128,128,128,128),
-                ..Default::default()
-            },
-        ),
-    );
+    //Scatter a seeded decision-boundary cloud over the input domain
+    let mut sampler = BoundarySampler::new(42);
+    let mut sample_points = build_sample_points(&context, &sampler, &network, num_inputs);
 
-    let mut weight_line_1 = CpuMesh::cylinder(8);
-    weight_line_1.transform(&Mat4::from_nonuniform_scale(4.0,  0.1, 0.1));
-    
-    weight_line_1.transform(&Mat4::from_translation(Vec3::new(-3.0, 2.0, 0.0))).unwrap();
-
-    let mut weight_line_1_mesh = Gm::new(
-        Mesh::new(&context, &weight_line_1),
-        PhysicalMaterial::new_opaque(
-            &context,
-            &CpuMaterial {
-                albedo: Color::new(128,128,128,128),
-                ..Default::default()
-            },
-        ),
-    );
-
-    let mut line = CpuMesh::cylinder(8);
-    line.transform(&Mat4::from_nonuniform_scale(4.0,  0.1, 0.1));
-    line.transform(&Mat4::from_translation(Vec3::new(-3.0, 0.0, 0.0))).unwrap();
-
-    let mut line_mesh = Gm::new(
-        Mesh::new(&context, &line),
-        PhysicalMaterial::new_opaque(
-            &context,
-            &CpuMaterial {
-                albedo: Color::new(128,128,128,128),
-                ..Default::default()
-            },
-        ),
-    );
+    //HDR render target and bloom settings so firing nodes can glow
+    let mut bloom_pipeline = BloomPipeline::new(&context, window.viewport());
+    let mut bloom_settings = BloomSettings::default();
 
     //Create GUI context for sidebar
     let mut gui = three_d::GUI::new(&context);
     let mut selected_object: Option<String> = None;
 
-    //Get prediction from perceptron
-    for input in &inputs {
-        let prediction = perceptron.predict(input);
-        let message = format!("Input: {:?} => Prediction: {}", input, prediction);
-        console::log_1(&message.into());
-    }
+    // Which node/edge the cursor is over, and which is locked in by a click.
+    let mut hovered_node: Option<usize> = None;
+    let mut hovered_edge: Option<usize> = None;
+    let mut locked_node: Option<usize> = None;
+    let mut locked_edge: Option<usize> = None;
+    // Only re-pick once the cursor has moved this many pixels since the last
+    // pick; clicks always re-pick regardless.
+    const HOVER_PICK_THRESHOLD_PX: f32 = 6.0;
+    let mut last_hover_position: Option<PhysicalPoint> = None;
+
+    let node_label = |network: &Network, node_ref: &NodeRef| match *node_ref {
+        NodeRef::Input(i) => format!("Input {}", i + 1),
+        NodeRef::Neuron { layer, neuron } => {
+            if layer == network.layers.len() - 1 {
+                format!("Output Node {}", neuron + 1)
+            } else {
+                format!("Layer {} Neuron {}", layer + 1, neuron + 1)
+            }
+        }
+    };
 
     // render loop
     window.render_loop(move |mut frame_input| {
         let mut change = frame_input.first_frame;
         change |= camera.set_viewport(frame_input.viewport);
 
-        //handle mouse click events
+        //handle mouse hover/click events
         for event in frame_input.events.iter() {
-            if let Event::MousePress {
-                button, position, ..
-            } = event
-            {
-                if *button == MouseButton::Left {
-
-                    //This has to be in it's own scope for sake of satisfying rust's owernship laws. Will throw a borrow error otherwise
-                    //This bit sets all the meshes to grey whenever a new mesh is selected/ user selects empty space
-                    {
-                        let mut meshes = vec![
-                            &mut sphere_mesh,
-                            &mut output_node_mesh,
-                            &mut line_mesh,
-                            &mut input_node_1_mesh,
-                            &mut weight_line_1_mesh
-                        ];
-                        for mesh in &mut meshes {
-                            mesh.material.albedo = Color::new(128,128,128,128);
-                        }
-                    }
+            let (position, is_click) = match event {
+                Event::MouseMotion { position, .. } => (Some(position), false),
+                Event::MousePress {
+                    button: MouseButton::Left,
+                    position,
+                    ..
+                } => (Some(position), true),
+                _ => (None, false),
+            };
 
-                    //if user clicks inside of a mesh, select that mesh
-                    if let Some(pick) = pick(&context, &camera, position, &sphere_mesh) {
-                        sphere_mesh.material.albedo = Color::RED;
-                        selected_object = Some("Sphere".to_string());
-                        change = true;
-                    } else if let Some(pick) = pick(&context, &camera, position, &output_node_mesh) {
-                        output_node_mesh.material.albedo = Color::RED;
-                        selected_object = Some("Output Node".to_string());
-                        change = true;
-                    } else if let Some(pick) = pick(&context, &camera, position, &weight_line_1_mesh) {
-                        weight_line_1_mesh.material.albedo = Color::RED;
-                        selected_object = Some(perceptron.weights[0].to_string());
-                        change = true;
-                    } 
-                    else if let Some(pick) = pick(&context, &camera, position, &line_mesh) {
-                        line_mesh.material.albedo = Color::RED;
-                        selected_object = Some(perceptron.weights[1].to_string());
-                        change = true;
-                    }
-                    else if let Some(pick) = pick(&context, &camera, position, &input_node_1_mesh) {
-                        input_node_1_mesh.material.albedo = Color::RED;
-                        selected_object = Some("Input 1".to_string());
-                        change = true;
-                    }
+            let Some(position) = position else { continue };
+
+            let moved_enough = match last_hover_position {
+                Some(last) => {
+                    let dx = position.x - last.x;
+                    let dy = position.y - last.y;
+                    dx * dx + dy * dy >= HOVER_PICK_THRESHOLD_PX * HOVER_PICK_THRESHOLD_PX
                 }
+                None => true,
+            };
+
+            if is_click || moved_enough {
+                hovered_node = scene
+                    .node_meshes
+                    .iter()
+                    .position(|mesh| pick(&context, &camera, position, mesh).is_some());
+                hovered_edge = if hovered_node.is_some() {
+                    None
+                } else {
+                    scene
+                        .edge_meshes
+                        .iter()
+                        .position(|mesh| pick(&context, &camera, position, mesh).is_some())
+                };
+                last_hover_position = Some(*position);
+                change = true;
+            }
+
+            if is_click {
+                locked_node = hovered_node;
+                locked_edge = if hovered_node.is_some() { None } else { hovered_edge };
+                selected_object = if let Some(i) = locked_node {
+                    Some(node_label(&network, &scene.node_refs[i]))
+                } else if let Some(i) = locked_edge {
+                    let edge_ref = scene.edge_refs[i];
+                    let weight = network.layers[edge_ref.layer].perceptrons[edge_ref.to].weights[edge_ref.from];
+                    Some(weight.to_string())
+                } else {
+                    None
+                };
             }
         }
 
@@ -257,25 +952,139 @@ pub async fn run() {
                     if let Some(object) = &selected_object {
                         ui.label(format!("Selected: {}", object));
                     }
+
+                    ui.separator();
+                    ui.label("Training");
+                    ui.horizontal(|ui| {
+                        if ui.button("Step epoch").clicked() {
+                            step_training(
+                                &mut network,
+                                &inputs,
+                                &targets,
+                                &mut training_history,
+                                &mut best_errors,
+                                &mut epochs_since_improvement,
+                            );
+                            refresh_edge_colors(&mut scene, &network);
+                            refresh_node_glow(&mut scene, &network, inputs[current_input_index]);
+                            sample_points = build_sample_points(&context, &sampler, &network, num_inputs);
+                            change = true;
+                        }
+                        if ui.button(if training_running { "Pause" } else { "Run" }).clicked() {
+                            training_running = !training_running;
+                            training_stalled = false;
+                        }
+                        if ui.button("Reset weights").clicked() {
+                            network = Network::new(&layer_sizes, learning_rate);
+                            training_history.clear();
+                            training_running = false;
+                            best_errors = usize::MAX;
+                            epochs_since_improvement = 0;
+                            training_stalled = false;
+                            refresh_edge_colors(&mut scene, &network);
+                            refresh_node_glow(&mut scene, &network, inputs[current_input_index]);
+                            sample_points = build_sample_points(&context, &sampler, &network, num_inputs);
+                            change = true;
+                        }
+                    });
+                    if training_stalled {
+                        ui.label(format!(
+                            "Training stalled (best: {} errors) - this dataset may not be linearly separable",
+                            best_errors
+                        ));
+                    }
+                    let error_points: egui_plot::PlotPoints = training_history
+                        .iter()
+                        .enumerate()
+                        .map(|(epoch, &errors)| [epoch as f64, errors])
+                        .collect();
+                    egui_plot::Plot::new("training_error_plot")
+                        .height(120.0)
+                        .show(ui, |plot_ui| plot_ui.line(egui_plot::Line::new(error_points)));
+
+                    ui.separator();
+                    ui.label("Decision boundary");
+                    let mode_before = sampler.mode;
+                    ui.radio_value(&mut sampler.mode, SampleMode::Interior, "Interior");
+                    ui.radio_value(&mut sampler.mode, SampleMode::Boundary, "Boundary");
+                    if sampler.mode != mode_before {
+                        sample_points = build_sample_points(&context, &sampler, &network, num_inputs);
+                        change = true;
+                    }
+
+                    ui.separator();
+                    ui.label("Activation glow");
+                    let input_before = current_input_index;
+                    ComboBox::from_label("Active input")
+                        .selected_text(format!("{:?}", inputs[current_input_index]))
+                        .show_ui(ui, |ui| {
+                            for (i, input) in inputs.iter().enumerate() {
+                                ui.selectable_value(&mut current_input_index, i, format!("{:?}", input));
+                            }
+                        });
+                    if current_input_index != input_before {
+                        refresh_node_glow(&mut scene, &network, inputs[current_input_index]);
+                        change = true;
+                    }
+                    if ui.add(Slider::new(&mut bloom_settings.threshold, 0.1..=3.0).text("Bloom threshold")).changed() {
+                        change = true;
+                    }
+                    if ui.add(Slider::new(&mut bloom_settings.intensity, 0.0..=3.0).text("Bloom intensity")).changed() {
+                        change = true;
+                    }
                 });
                 panel_width = gui_context.used_rect().width();
             },
         );
 
+        if training_running {
+            let errors = step_training(
+                &mut network,
+                &inputs,
+                &targets,
+                &mut training_history,
+                &mut best_errors,
+                &mut epochs_since_improvement,
+            );
+            refresh_edge_colors(&mut scene, &network);
+            refresh_node_glow(&mut scene, &network, inputs[current_input_index]);
+            sample_points = build_sample_points(&context, &sampler, &network, num_inputs);
+            change = true;
+            if errors == 0 {
+                training_running = false;
+            } else if epochs_since_improvement >= STALL_WINDOW || training_history.len() >= MAX_TRAINING_EPOCHS {
+                // Stalled or hit the cap - stop instead of spinning forever.
+                training_running = false;
+                training_stalled = true;
+            }
+        }
+
         change |= control.handle_events(&mut camera, &mut frame_input.events);
 
         // draw three-d objects
         if change {
-            frame_input
-                .screen()
-                .clear(ClearState::color_and_depth(1.0, 1.0, 1.0, 1.0, 1.0))
-                .render(
-                    &camera,
-                    &[&sphere_mesh, &output_node_mesh, &line_mesh, &input_node_1_mesh, &weight_line_1_mesh],
-                    &[&ambient, &directional],
-                )
-                .write(|| gui.render()
+            let node_highlights = highlight_objects(&mut scene.node_highlights, hovered_node, locked_node);
+            let edge_highlights = highlight_objects(&mut scene.edge_highlights, hovered_edge, locked_edge);
+            let objects: Vec<&dyn Object> = scene
+                .node_meshes
+                .iter()
+                .map(|m| m as &dyn Object)
+                .chain(scene.edge_meshes.iter().map(|m| m as &dyn Object))
+                .chain(std::iter::once(&sample_points as &dyn Object))
+                .chain(node_highlights)
+                .chain(edge_highlights)
+                .collect();
+            let mut screen = frame_input.screen();
+            render_with_bloom(
+                &mut bloom_pipeline,
+                &context,
+                &mut screen,
+                &camera,
+                &objects,
+                &[&ambient, &directional],
+                &bloom_settings,
             );
+            screen.write(|| gui.render());
         }
 
         FrameOutput {